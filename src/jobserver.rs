@@ -0,0 +1,155 @@
+//! Client/server support for the GNU make jobserver protocol, so that
+//! `parallel` cooperates with `make -j` (and other jobserver-aware tools)
+//! instead of oversubscribing the machine with its own `ncores` workers.
+//!
+//! The protocol hands out tokens through a pipe: one byte read from the
+//! pipe is one slot to run a job in, and the byte must be written back
+//! when the job finishes. Every participating process also owns one
+//! *implicit* token that never touches the pipe, so a lone job can always
+//! make progress.
+
+extern crate libc;
+
+use std::env;
+use std::io;
+
+/// A handle to a jobserver inherited from a parent `make`/`parallel`
+/// process via `MAKEFLAGS`.
+pub struct Client {
+    read_fd: i32,
+    write_fd: i32,
+}
+
+/// A single occupied slot. The implicit slot never touches the pipe;
+/// an acquired slot writes its byte back to the pipe when dropped, so a
+/// token can never be leaked on an early return or panic.
+pub enum Token {
+    Implicit,
+    Acquired { write_fd: i32, byte: u8 },
+}
+
+impl Client {
+    /// Parses `MAKEFLAGS` for a `--jobserver-auth=R,W` or the older
+    /// `--jobserver-fds=R,W` token naming the two inherited pipe fds.
+    pub fn from_env() -> Option<Client> {
+        let makeflags = env::var("MAKEFLAGS").ok()?;
+        for flag in makeflags.split_whitespace() {
+            let value = if let Some(value) = flag.strip_prefix("--jobserver-auth=") {
+                value
+            } else if let Some(value) = flag.strip_prefix("--jobserver-fds=") {
+                value
+            } else {
+                continue;
+            };
+
+            let mut fds = value.splitn(2, ',');
+            let read_fd = fds.next().and_then(|v| v.parse::<i32>().ok());
+            let write_fd = fds.next().and_then(|v| v.parse::<i32>().ok());
+            if let (Some(read_fd), Some(write_fd)) = (read_fd, write_fd) {
+                return Some(Client { read_fd, write_fd });
+            }
+        }
+        None
+    }
+
+    /// Creates a new jobserver pipe pre-filled with `slots` tokens and
+    /// exports `MAKEFLAGS` so that child processes (including nested
+    /// `parallel`/`make` invocations) inherit it.
+    pub fn serve(slots: usize) -> io::Result<()> {
+        let mut fds = [0i32; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let (read_fd, write_fd) = (fds[0], fds[1]);
+        for _ in 0..slots {
+            if unsafe { libc::write(write_fd, b"+".as_ptr() as *const _, 1) } != 1 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        env::set_var("MAKEFLAGS", format!("--jobserver-auth={},{}", read_fd, write_fd));
+        Ok(())
+    }
+
+    /// Blocks until a token becomes available, reading exactly one byte
+    /// from the jobserver pipe. The returned `Token` releases the byte
+    /// back to the pool when it is dropped.
+    pub fn acquire(&self) -> io::Result<Token> {
+        let mut byte = [0u8; 1];
+        loop {
+            let read = unsafe { libc::read(self.read_fd, byte.as_mut_ptr() as *mut _, 1) };
+            if read == 1 {
+                return Ok(Token::Acquired { write_fd: self.write_fd, byte: byte[0] });
+            } else if read < 0 {
+                let why = io::Error::last_os_error();
+                if why.kind() == io::ErrorKind::Interrupted { continue; }
+                return Err(why);
+            } else {
+                // The server's write end was closed; fall back to the implicit slot.
+                return Ok(Token::Implicit);
+            }
+        }
+    }
+}
+
+impl Drop for Token {
+    fn drop(&mut self) {
+        if let Token::Acquired { write_fd, byte } = *self {
+            loop {
+                let wrote = unsafe { libc::write(write_fd, &byte as *const u8 as *const _, 1) };
+                if wrote >= 0 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipe() -> (i32, i32) {
+        let mut fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        (fds[0], fds[1])
+    }
+
+    #[test]
+    fn from_env_parses_the_current_auth_flag() {
+        env::set_var("MAKEFLAGS", "-j8 --jobserver-auth=11,22 --other-flag");
+        let client = Client::from_env().unwrap();
+        assert_eq!((client.read_fd, client.write_fd), (11, 22));
+        env::remove_var("MAKEFLAGS");
+    }
+
+    #[test]
+    fn from_env_parses_the_older_fds_flag() {
+        env::set_var("MAKEFLAGS", "--jobserver-fds=3,4");
+        let client = Client::from_env().unwrap();
+        assert_eq!((client.read_fd, client.write_fd), (3, 4));
+        env::remove_var("MAKEFLAGS");
+    }
+
+    #[test]
+    fn from_env_is_none_without_a_jobserver_flag() {
+        env::set_var("MAKEFLAGS", "-j8");
+        assert!(Client::from_env().is_none());
+        env::remove_var("MAKEFLAGS");
+    }
+
+    #[test]
+    fn acquire_takes_an_available_token_then_releases_it_on_drop() {
+        let (read_fd, write_fd) = pipe();
+        unsafe { assert_eq!(libc::write(write_fd, b"+".as_ptr() as *const _, 1), 1); }
+        let client = Client { read_fd, write_fd };
+
+        let token = client.acquire().unwrap();
+        assert!(matches!(token, Token::Acquired { .. }));
+
+        // The pipe is now empty, so the byte only reappears once `token`
+        // is dropped and writes it back.
+        let mut buf = [0u8; 1];
+        drop(token);
+        assert_eq!(unsafe { libc::read(read_fd, buf.as_mut_ptr() as *mut _, 1) }, 1);
+    }
+}
@@ -1,29 +1,33 @@
+extern crate libc;
 extern crate num_cpus;
 
+mod jobserver;
+mod sshlogin;
+mod raise_fd_limit;
+
 use std::env;
-use std::io::{self, Write, StderrLock};
-use std::process::{Command, exit};
+use std::fs;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write, StderrLock};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, exit};
 use std::thread::{self, JoinHandle};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
-
-/* TODO: Functionality can be increased to accept the following syntaxes from GNU Parallel:
- - Stdin support is currently missing.
- - {N}, {N.}, etc.
- - parallel command {1} {2} {3} ::: 1 2 3 ::: 4 5 6 ::: 7 8 9
- - paralllel command ::: a b c :::+ 1 2 3 ::: d e f :::+ 4 5 6
-*/
+use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
 
 fn main() {
+    // Each concurrent job can hold several file descriptors open at once
+    // (stdin/stdout/stderr, plus a remote transfer's own), so raise the
+    // soft limit before `-j`/`--jobs` starts spawning them. Best-effort:
+    // a failure here just leaves the environment's existing limit in place.
+    let _ = raise_fd_limit::raise_fd_limit();
+
     let stderr = io::stderr();
-    let mut ncores = num_cpus::get();
-    let mut command = String::new();
-    let mut arg_tokens = Vec::new();
-    let mut inputs = Vec::new();
+    let mut cfg = Args::new();
 
     // Let's collect all parameters that we need from the program's arguments.
     // If an error is returned, this will handle that error as efficiently as possible.
-    if let Err(why) = parse_arguments(&mut ncores, &mut command, &mut arg_tokens, &mut inputs) {
+    if let Err(why) = cfg.parse() {
         let mut stderr = stderr.lock();
         let _ = stderr.write(b"parallel: parsing error: ");
         match why {
@@ -32,10 +36,41 @@ fn main() {
                 let _ = stderr.write(value.as_bytes());
                 let _ = stderr.write(b"', is not a number.\n");
             },
+            ParseErr::BatchSizeNaN(value) => {
+                let _ = stderr.write(b"-N parameter, '");
+                let _ = stderr.write(value.as_bytes());
+                let _ = stderr.write(b"', is not a number.\n");
+            },
+            ParseErr::RetriesNaN(value) => {
+                let _ = stderr.write(b"--retries parameter, '");
+                let _ = stderr.write(value.as_bytes());
+                let _ = stderr.write(b"', is not a number.\n");
+            },
+            ParseErr::RetryDelayNaN(value) => {
+                let _ = stderr.write(b"--retry-delay parameter, '");
+                let _ = stderr.write(value.as_bytes());
+                let _ = stderr.write(b"', is not a number.\n");
+            },
+            ParseErr::BlockSizeNaN(value) => {
+                let _ = stderr.write(b"--block-size parameter, '");
+                let _ = stderr.write(value.as_bytes());
+                let _ = stderr.write(b"', is not a number.\n");
+            },
             _ => {
                 let message: &[u8] = match why {
-                    ParseErr::InputVarsNotDefined => b"input variables were not defined.\n",
                     ParseErr::JobsNoValue         => b"no jobs parameter was defined.\n",
+                    ParseErr::BatchSizeNoValue    => b"no -N parameter was defined.\n",
+                    ParseErr::BatchSizeZero       => b"-N parameter must be at least 1.\n",
+                    ParseErr::SshLoginNoValue     => b"no --sshlogin parameter was defined.\n",
+                    ParseErr::SshLoginCapacityZero => b"--sshlogin capacity must be at least 1.\n",
+                    ParseErr::TransferFileNoValue => b"no --transferfile parameter was defined.\n",
+                    ParseErr::ReturnNoValue       => b"no --return parameter was defined.\n",
+                    ParseErr::WorkdirNoValue      => b"no --workdir parameter was defined.\n",
+                    ParseErr::RetriesNoValue      => b"no --retries parameter was defined.\n",
+                    ParseErr::RetryDelayNoValue   => b"no --retry-delay parameter was defined.\n",
+                    ParseErr::HaltOnErrorNoValue  => b"no --halt-on-error parameter was defined.\n",
+                    ParseErr::TmpdirNoValue       => b"no --tmpdir parameter was defined.\n",
+                    ParseErr::BlockSizeNoValue    => b"no --block-size parameter was defined.\n",
                     _ => unreachable!()
                 };
                 let _ = stderr.write(message);
@@ -44,72 +79,208 @@ fn main() {
         exit(1);
     }
 
+    // `--transferfile`/`--return` name the file to copy per job (typically
+    // `{}`, the job's own input), so tokenize them the same way as the
+    // command template and render them per batch instead of copying the
+    // literal flag text on every job.
+    let transferfile_tokens = cfg.transferfile.as_deref().map(tokenize);
+    let return_tokens = cfg.return_file.as_deref().map(tokenize);
+
+    // If we were asked to act as the jobserver, set up the pipe and export
+    // `MAKEFLAGS` before we (or any nested `parallel`/`make`) look for one.
+    if cfg.act_as_jobserver {
+        if let Err(why) = jobserver::Client::serve(cfg.ncores.saturating_sub(1)) {
+            let mut stderr = stderr.lock();
+            let _ = writeln!(&mut stderr, "parallel: unable to become jobserver: {}", why);
+        }
+    }
+
+    // If a jobserver was inherited via `MAKEFLAGS`, cooperate with it so we
+    // don't oversubscribe the machine's build-wide parallelism budget.
+    let jobserver_client = jobserver::Client::from_env().map(Arc::new);
+    // This process's one implicit token is shared by all of its worker
+    // threads: whichever job finds it free runs without reading from the
+    // jobserver pipe, and frees it again when that job finishes.
+    let implicit_token_taken = Arc::new(AtomicBool::new(false));
+
+    // Remote hosts registered via `--sshlogin` are scheduled round-robin,
+    // with each host's own capacity gating how many jobs run there at once.
+    let ssh_scheduler = if cfg.sshlogins.is_empty() {
+        None
+    } else {
+        Some(Arc::new(sshlogin::Scheduler::new(cfg.sshlogins)))
+    };
+
+    // Shared `--halt-on-error` state; `None` means halting is disabled and
+    // a failing job is just reported as usual.
+    let halt = if cfg.halt_on_error { Some(Arc::new(HaltState::new())) } else { None };
+
     // If no command was given, then the inputs are actually commands themselves.
-    let input_is_command = command.is_empty();
+    let input_is_command = cfg.command.is_empty();
 
-    // It will be useful to know the number of inputs, to know when to quit.
-    let num_inputs = inputs.len();
+    // With no `:::` list at all, or an explicit `::: -`, read input records
+    // from stdin as they arrive instead of requiring them all up front, so
+    // that `somecmd | parallel -j8 do-thing` works as a true pipe.
+    let use_stdin = cfg.input_groups.is_empty() || cfg.input_groups.iter().all(|group| {
+        group.len() == 1 && group[0].len() == 1 && group[0][0] == "-"
+    });
+
+    let inputs = if use_stdin {
+        Inputs::Stdin {
+            reader: Mutex::new(io::BufReader::new(io::stdin())),
+            next_job: AtomicUsize::new(0),
+            null_terminated: cfg.null_terminated,
+            pending: Mutex::new(None),
+        }
+    } else {
+        // Expand the `:::`/`:::+` input groups into the cartesian product of
+        // their independent lists, with `:::+` lists zipped in lockstep
+        // against the list they were linked to.
+        let data = generate_jobs(&cfg.input_groups);
+        let total_bytes: usize = data.iter().map(|row| whole_line(row).len() + 1).sum();
+
+        // Small input lists stay entirely in memory; only once they'd
+        // cross `--block-size` bytes do we pay for spilling to disk.
+        if total_bytes > cfg.block_size {
+            match spill_to_disk(&data, cfg.tmpdir.as_deref()) {
+                Ok((file, offsets)) => Inputs::Disk {
+                    file: Mutex::new(file), offsets, counter: AtomicUsize::new(0), total: data.len()
+                },
+                Err(why) => {
+                    let mut stderr = stderr.lock();
+                    let _ = writeln!(&mut stderr, "parallel: unable to spill inputs to disk: {}", why);
+                    Inputs::List { counter: AtomicUsize::new(0), total: data.len(), data }
+                }
+            }
+        } else {
+            Inputs::List { counter: AtomicUsize::new(0), total: data.len(), data }
+        }
+    };
 
-    // Stores the next input to be processed
-    let shared_counter = Arc::new(AtomicUsize::new(0));
+    // Under `-X`, a batch grows until it would cross this many bytes of
+    // arguments; under plain `-N`, every batch is a fixed `batch_size`.
+    let arg_max_limit = if cfg.batch_auto { Some(arg_max()) } else { None };
 
-    // We will share the same list of inputs with each thread.
+    // We will share the same source of inputs with each thread.
     let shared_input = Arc::new(inputs);
 
     // First we will create as many threads as `ncores` specifies.
     // The `threads` vector will contain the thread handles needed to
     // know when to quit the program.
-    let mut threads: Vec<JoinHandle<()>> = Vec::with_capacity(ncores);
-    for slot in 1..ncores+1 {
+    let mut threads: Vec<JoinHandle<()>> = Vec::with_capacity(cfg.ncores);
+    for slot in 1..cfg.ncores+1 {
         // The command that each input variable will be sent to.
-        let command = command.clone();
+        let command = cfg.command.clone();
         // The arguments for the command.
-        let argument_tokens = arg_tokens.clone();
-        // Allow the thread to gain access to the list of inputs.
+        let argument_tokens = cfg.arg_tokens.clone();
+        // Allow the thread to gain access to the source of inputs.
         let input = shared_input.clone();
-        // Allow the thread to access the current command counter
-        let counter = shared_counter.clone();
-        // Allow the thread to know when it's time to stop.
-        let num_inputs = num_inputs;
+        // Allow the thread to cooperate with an inherited jobserver, if any.
+        let jobserver_client = jobserver_client.clone();
+        let implicit_token_taken = implicit_token_taken.clone();
+        let ssh_scheduler = ssh_scheduler.clone();
+        let transferfile_tokens = transferfile_tokens.clone();
+        let return_tokens = return_tokens.clone();
+        let workdir = cfg.workdir.clone();
+        let halt = halt.clone();
+        let batch_size = cfg.batch_size;
+        let retries = cfg.retries;
+        let retry_delay = cfg.retry_delay;
 
         // The actual thread where the work will happen on incoming data.
         let handle: JoinHandle<()> = thread::spawn(move || {
             let slot_number = slot;
             let stderr = io::stderr();
             loop {
-                // Obtain the Nth item and it's job ID from the list of inputs.
-                let (input_var, job_id) = {
-                    // Atomically increment the counter
-                    let old_counter = counter.fetch_add(1, Ordering::SeqCst);
-                    if old_counter >= num_inputs {
-                        break
-                    } else {
-                        let input_var = &input[old_counter];
-                        let job_id = old_counter + 1;
-                        (input_var, job_id)
-                    }
+                // Once another worker's job has triggered
+                // `--halt-on-error`, stop pulling new work.
+                if halt.as_ref().is_some_and(|halt| halt.triggered.load(Ordering::SeqCst)) {
+                    break;
+                }
+
+                // Atomically claim the next batch of inputs, blocking on
+                // stdin if that's the source: under `-N`/`-X` this can be
+                // more than one row, but it is always claimed as a whole so
+                // batches never interleave across threads.
+                let (batch, job_id) = match input.claim(batch_size, arg_max_limit) {
+                    Some(result) => result,
+                    None => break
                 };
 
+                // Acquire a jobserver token before running the job, if one was
+                // inherited. Holding it in `_token` guarantees it's written
+                // back as soon as the job finishes, even if the code below
+                // returns early.
+                let _token = acquire_token(&jobserver_client, &implicit_token_taken);
+
+                let (slot, job) = (slot_number.to_string(), job_id.to_string());
+
+                // Claim a remote host's slot, if any were registered, and
+                // transfer the input file to it before dispatching the job.
+                // The slot is released as soon as it drops at the end of
+                // this iteration. `--transferfile {}` names the job's own
+                // input, so it's rendered against the batch's first row
+                // the same way the command template is.
+                let remote_slot = ssh_scheduler.as_ref().map(|scheduler| scheduler.acquire());
+                if let Some(ref rslot) = remote_slot {
+                    if let Some(path) = transferfile_tokens.as_ref().and_then(|tokens| {
+                        batch.first().map(|columns| render_tokens(columns, tokens, &slot, &job))
+                    }) {
+                        if let Err(why) = sshlogin::transfer_to(rslot.host(), &path) {
+                            let mut stderr = stderr.lock();
+                            let _ = writeln!(&mut stderr, "parallel: transfer error: {}: {}", path, why);
+                        }
+                    }
+                }
+                let remote = remote_slot.as_ref().map(|rslot| (rslot.host(), workdir.as_deref()));
+
                 if input_is_command {
-                    // The inputs are actually the commands.
-                    let mut iterator = input_var.split_whitespace();
-                    let actual_command = iterator.next().unwrap();
-                    let args = iterator.collect::<Vec<&str>>();
-                    if let Err(_) = Command::new(actual_command).args(&args).status() {
-                        let mut stderr = stderr.lock();
-                        let _ = stderr.write(b"parallel: command error: ");
-                        let _ = stderr.write(input_var.as_bytes());
-                        let _ = stderr.write(b"\n");
+                    // The inputs are actually the commands; batching doesn't
+                    // apply here since each row names a different program.
+                    for input_var in &batch {
+                        let line = whole_line(input_var);
+                        let mut iterator = line.split_whitespace();
+                        // A blank/whitespace-only record names no command; skip it
+                        // rather than unwrapping `None`, since piped command
+                        // generators routinely emit a trailing blank line.
+                        let actual_command = match iterator.next() {
+                            Some(actual_command) => actual_command,
+                            None => continue
+                        };
+                        let args = iterator.collect::<Vec<&str>>();
+                        let code = run_with_retries(|| dispatch(actual_command, &args, remote),
+                            retries, retry_delay);
+                        if code != 0 {
+                            let mut stderr = stderr.lock();
+                            let _ = stderr.write(b"parallel: command error: ");
+                            let _ = stderr.write(line.as_bytes());
+                            let _ = stderr.write(b"\n");
+                            register_halt(&halt, code);
+                        }
                     }
                 } else {
-                    // Build a command by merging the command template with the input,
-                    // and then execute that command.
-                    let (slot, job) = (slot_number.to_string(), job_id.to_string());
-                    if let Err(cmd_err) = cmd_builder(input_var, &command, &argument_tokens,
-                        &slot, &job)
-                    {
+                    // Build a command by merging the command template with the
+                    // (possibly batched) input, and then execute that command,
+                    // re-running it under `--retries` if it exits non-zero.
+                    let arguments = render_batch_arguments(&batch, &argument_tokens, &slot, &job);
+                    let arg_list = arguments.split_whitespace().map(str::to_owned).collect::<Vec<String>>();
+                    let arg_refs = arg_list.iter().map(String::as_str).collect::<Vec<&str>>();
+                    let code = run_with_retries(|| dispatch(&command, &arg_refs, remote), retries, retry_delay);
+                    if code != 0 {
                         let mut stderr = stderr.lock();
-                        cmd_err.handle(&mut stderr);
+                        CommandErr::Failed(command.clone(), arg_list).handle(&mut stderr);
+                        register_halt(&halt, code);
+                    }
+                }
+
+                if let Some(ref rslot) = remote_slot {
+                    if let Some(path) = return_tokens.as_ref().and_then(|tokens| {
+                        batch.first().map(|columns| render_tokens(columns, tokens, &slot, &job))
+                    }) {
+                        if let Err(why) = sshlogin::transfer_from(rslot.host(), &path) {
+                            let mut stderr = stderr.lock();
+                            let _ = writeln!(&mut stderr, "parallel: transfer error: {}: {}", path, why);
+                        }
                     }
                 }
             }
@@ -121,6 +292,275 @@ fn main() {
     }
 
     for thread in threads.into_iter() { thread.join().unwrap(); }
+
+    // If `--halt-on-error` stopped the run early, propagate the exit code
+    // of the job that triggered it as our own.
+    if let Some(ref halt) = halt {
+        if halt.triggered.load(Ordering::SeqCst) {
+            exit(halt.exit_code.load(Ordering::SeqCst) as i32);
+        }
+    }
+}
+
+/// Where a worker pulls its next job from: a precomputed list built from
+/// `:::` input groups, or a streaming reader over stdin (used when no
+/// `:::` list was given, or an explicit `::: -` was), so that piped input
+/// is processed as it arrives rather than read to completion up front.
+enum Inputs {
+    List { data: Vec<Vec<String>>, counter: AtomicUsize, total: usize },
+    /// A `:::` input list large enough to have crossed `--block-size`,
+    /// spilled to a NUL-joined, newline-separated row per line in a temp
+    /// file so it doesn't have to stay resident in memory for the whole
+    /// run. `offsets[i]` is the byte offset of row `i`; the extra trailing
+    /// entry marks end-of-file.
+    Disk { file: Mutex<fs::File>, offsets: Vec<u64>, counter: AtomicUsize, total: usize },
+    Stdin {
+        reader: Mutex<io::BufReader<io::Stdin>>,
+        next_job: AtomicUsize,
+        null_terminated: bool,
+        /// A record already pulled off `reader` to measure whether it fit
+        /// under `-X`'s byte budget, but that didn't and so was held back
+        /// for the next batch instead of being read twice.
+        pending: Mutex<Option<String>>,
+    }
+}
+
+impl Inputs {
+    /// Atomically claims the next batch of inputs for a worker thread,
+    /// returning the batch and the 1-based id of its first job, or `None`
+    /// once the source is exhausted.
+    fn claim(&self, batch_size: usize, arg_max_limit: Option<usize>) -> Option<(Vec<Vec<String>>, usize)> {
+        match *self {
+            Inputs::List { ref data, ref counter, total } => {
+                claim_batch(counter, total, batch_size, arg_max_limit, |i| whole_line(&data[i]).len())
+                    .map(|(start, end)| (data[start..end].to_vec(), start + 1))
+            },
+            Inputs::Disk { ref file, ref offsets, ref counter, total } => {
+                claim_batch(counter, total, batch_size, arg_max_limit,
+                    |i| (offsets[i + 1] - offsets[i]) as usize - 1)
+                    .map(|(start, end)| {
+                        let mut file = file.lock().unwrap();
+                        (read_rows(&mut file, offsets, start, end), start + 1)
+                    })
+            },
+            Inputs::Stdin { ref reader, ref next_job, null_terminated, ref pending } => {
+                let mut reader = reader.lock().unwrap();
+                let mut pending = pending.lock().unwrap();
+                let mut batch = Vec::new();
+
+                match arg_max_limit {
+                    // The total size of a streamed source isn't known up
+                    // front, so a batch grows one record at a time,
+                    // carrying over whichever record didn't fit into the
+                    // next call instead of reading it twice.
+                    Some(limit) => {
+                        let mut used = 0usize;
+                        while let Some(record) = pending.take().or_else(|| read_record(&mut reader, null_terminated)) {
+                            let record_len = record.len() + 1;
+                            if !batch.is_empty() && used + record_len > limit {
+                                *pending = Some(record);
+                                break;
+                            }
+                            used += record_len;
+                            batch.push(vec![record]);
+                        }
+                    },
+                    None => {
+                        while batch.len() < batch_size {
+                            match pending.take().or_else(|| read_record(&mut reader, null_terminated)) {
+                                Some(record) => batch.push(vec![record]),
+                                None => break
+                            }
+                        }
+                    }
+                }
+
+                if batch.is_empty() { return None; }
+                let start = next_job.fetch_add(batch.len(), Ordering::SeqCst);
+                Some((batch, start + 1))
+            }
+        }
+    }
+}
+
+/// Reads one record from a streaming input source, splitting on `\n`, or
+/// on NUL when `-0`/`--null` was given (for filenames that may contain
+/// whitespace). Returns `None` on EOF.
+fn read_record(reader: &mut io::BufReader<io::Stdin>, null_terminated: bool) -> Option<String> {
+    let delimiter = if null_terminated { 0u8 } else { b'\n' };
+    let mut buffer = Vec::new();
+    match reader.read_until(delimiter, &mut buffer) {
+        Ok(0) => None,
+        Ok(_) => {
+            if buffer.last() == Some(&delimiter) { buffer.pop(); }
+            Some(String::from_utf8_lossy(&buffer).into_owned())
+        },
+        Err(_) => None
+    }
+}
+
+/// Queries the kernel's `ARG_MAX` so `-X` knows how many bytes of
+/// arguments a batch is allowed to grow to, falling back to a conservative
+/// 128 KiB if the platform doesn't report one.
+fn arg_max() -> usize {
+    let limit = unsafe { libc::sysconf(libc::_SC_ARG_MAX) };
+    if limit > 0 { limit as usize } else { 128 * 1024 }
+}
+
+/// The spill threshold used when `--block-size` isn't given.
+const DEFAULT_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Serializes `data` as one NUL-joined row per line into a temp file
+/// rooted at `tmpdir` (or the system temp directory), named after our own
+/// pid so concurrent runs don't collide, and unlinked immediately so it's
+/// cleaned up as soon as we exit. Returns the open file and the byte
+/// offset of the start of each row, with one extra trailing offset
+/// marking end-of-file.
+fn spill_to_disk(data: &[Vec<String>], tmpdir: Option<&Path>) -> io::Result<(fs::File, Vec<u64>)> {
+    let dir = tmpdir.map(Path::to_owned).unwrap_or_else(env::temp_dir);
+    let path = dir.join(format!("parallel_unprocessed_{}", process::id()));
+    let mut file = fs::OpenOptions::new().create(true).truncate(true).read(true).write(true).open(&path)?;
+    let _ = fs::remove_file(&path);
+
+    let mut offsets = Vec::with_capacity(data.len() + 1);
+    let mut offset = 0u64;
+    for row in data {
+        offsets.push(offset);
+        let line = row.join("\0");
+        file.write_all(line.as_bytes())?;
+        file.write_all(b"\n")?;
+        offset += line.len() as u64 + 1;
+    }
+    offsets.push(offset);
+    Ok((file, offsets))
+}
+
+/// Reads back the rows in `[start, end)` that `spill_to_disk` wrote,
+/// splitting each line on the NUL bytes used to join its columns.
+fn read_rows(file: &mut fs::File, offsets: &[u64], start: usize, end: usize) -> Vec<Vec<String>> {
+    let from = offsets[start];
+    let len = (offsets[end] - from) as usize;
+    let mut buffer = vec![0u8; len];
+    file.seek(SeekFrom::Start(from)).unwrap();
+    file.read_exact(&mut buffer).unwrap();
+    String::from_utf8_lossy(&buffer).lines()
+        .map(|line| line.split('\0').map(str::to_owned).collect())
+        .collect()
+}
+
+/// Atomically claims the next contiguous batch of inputs for a worker
+/// thread. Under plain `-N`, every batch is `batch_size` rows (the last
+/// one may be shorter); under `-X`, a batch grows one row at a time until
+/// adding another would cross `arg_max`. `row_len` reports a row's
+/// rendered byte length without fetching the row itself, so this works the
+/// same whether the rows live in memory or on disk. Returns `None` once
+/// every input has been claimed.
+fn claim_batch<F: Fn(usize) -> usize>(counter: &AtomicUsize, total: usize, batch_size: usize,
+    arg_max: Option<usize>, row_len: F) -> Option<(usize, usize)>
+{
+    loop {
+        let start = counter.load(Ordering::SeqCst);
+        if start >= total { return None; }
+
+        let end = match arg_max {
+            Some(limit) => {
+                let mut end = start + 1;
+                let mut used = row_len(start);
+                while end < total {
+                    let next_len = row_len(end) + 1;
+                    if used + next_len > limit { break; }
+                    used += next_len;
+                    end += 1;
+                }
+                end
+            },
+            None => (start + batch_size).min(total)
+        };
+
+        if counter.compare_exchange(start, end, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+            return Some((start, end));
+        }
+    }
+}
+
+/// Accounts for a job's jobserver slot for as long as it's held, whether
+/// that's a real token read from the pipe or the shared implicit slot that
+/// every participating process gets for free. Dropping it releases the
+/// slot, so the implicit slot becomes available again for the next job
+/// once the one holding it finishes, rather than being a one-time freebie.
+enum JobToken {
+    // Never read directly; held only so the jobserver token it wraps is
+    // released back to the pipe when this is dropped.
+    #[allow(dead_code)]
+    Acquired(jobserver::Token),
+    Implicit(Arc<AtomicBool>)
+}
+
+impl Drop for JobToken {
+    fn drop(&mut self) {
+        if let JobToken::Implicit(ref implicit_token_taken) = *self {
+            implicit_token_taken.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Acquires a jobserver token for the job about to run, if a jobserver was
+/// inherited. Whichever job finds the shared implicit slot free claims it
+/// for the duration of its own run; every other concurrent job blocks on
+/// the jobserver pipe instead.
+fn acquire_token(client: &Option<Arc<jobserver::Client>>, implicit_token_taken: &Arc<AtomicBool>)
+    -> Option<JobToken>
+{
+    let client = client.as_ref()?;
+    if !implicit_token_taken.swap(true, Ordering::SeqCst) {
+        Some(JobToken::Implicit(implicit_token_taken.clone()))
+    } else {
+        client.acquire().ok().map(JobToken::Acquired)
+    }
+}
+
+/// Shared across every worker thread under `--halt-on-error`, so that the
+/// first job to fail (after exhausting its retries) stops the rest from
+/// claiming new work, and its exit status becomes the whole process's.
+struct HaltState {
+    triggered: AtomicBool,
+    exit_code: AtomicIsize,
+}
+
+impl HaltState {
+    fn new() -> HaltState {
+        HaltState { triggered: AtomicBool::new(false), exit_code: AtomicIsize::new(0) }
+    }
+}
+
+/// Records the first failing exit code under `--halt-on-error`; later
+/// failures are dropped since only the first one determines our own exit
+/// status.
+fn register_halt(halt: &Option<Arc<HaltState>>, code: i32) {
+    if let Some(halt) = halt {
+        if halt.triggered.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+            halt.exit_code.store(code as isize, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Runs `attempt`, re-running it up to `retries` times (waiting
+/// `retry_delay` between each) as long as it keeps exiting non-zero.
+/// Returns the exit code of whichever attempt stopped the loop, or `1` if
+/// the command could not even be spawned.
+fn run_with_retries<F>(mut attempt: F, retries: usize, retry_delay: Duration) -> i32
+    where F: FnMut() -> io::Result<std::process::ExitStatus>
+{
+    let mut tries = 0;
+    loop {
+        let code = match attempt() {
+            Ok(status) => status.code().unwrap_or(1),
+            Err(_) => 1
+        };
+        if code == 0 || tries >= retries { return code; }
+        tries += 1;
+        if retry_delay != Duration::from_millis(0) { thread::sleep(retry_delay); }
+    }
 }
 
 enum CommandErr {
@@ -152,7 +592,15 @@ enum Token {
     Dirname,
     BaseAndExt,
     Slot,
-    Job
+    Job,
+    // `{N}`, `{N.}`, `{N/}`, `{N//}`, `{N/.}` — the same transforms as
+    // above, but applied to a single numbered input column (1-indexed in
+    // the template, stored here 0-indexed).
+    Argument(usize),
+    ArgumentRemoveExtension(usize),
+    ArgumentBasename(usize),
+    ArgumentDirname(usize),
+    ArgumentBaseAndExt(usize)
 }
 
 fn tokenize(template: &str) -> Vec<Token> {
@@ -189,51 +637,119 @@ fn tokenize(template: &str) -> Vec<Token> {
 
 fn match_token(pattern: &str) -> Option<Token> {
     match pattern {
-        "."  => Some(Token::RemoveExtension),
-        "#"  => Some(Token::Job),
-        "%"  => Some(Token::Slot),
-        "/"  => Some(Token::Basename),
-        "//" => Some(Token::Dirname),
-        "/." => Some(Token::BaseAndExt),
+        "."  => return Some(Token::RemoveExtension),
+        "#"  => return Some(Token::Job),
+        "%"  => return Some(Token::Slot),
+        "/"  => return Some(Token::Basename),
+        "//" => return Some(Token::Dirname),
+        "/." => return Some(Token::BaseAndExt),
+        _    => ()
+    }
+
+    // Everything else must be a numbered placeholder: one or more digits
+    // naming an input column, optionally followed by one of the same
+    // transform suffixes as above, e.g. `{2.}` or `{3//}`.
+    let digits = pattern.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 { return None; }
+    let column: usize = pattern[..digits].parse().ok()?;
+    if column == 0 { return None; }
+    let column = column - 1;
+
+    match &pattern[digits..] {
+        ""   => Some(Token::Argument(column)),
+        "."  => Some(Token::ArgumentRemoveExtension(column)),
+        "/"  => Some(Token::ArgumentBasename(column)),
+        "//" => Some(Token::ArgumentDirname(column)),
+        "/." => Some(Token::ArgumentBaseAndExt(column)),
         _    => None
     }
+}
+
+/// Joins every input column into the traditional single-line `{}` value.
+/// With only one `:::` list this is identical to that list's element.
+fn whole_line(columns: &[String]) -> String { columns.join(" ") }
 
+/// Fetches a numbered input column, or an empty string if the template
+/// named a column that this job doesn't have.
+fn column(columns: &[String], index: usize) -> &str {
+    columns.get(index).map(String::as_str).unwrap_or("")
 }
 
-/// Builds the command and executes it
-fn cmd_builder(input: &str, command: &str, arg_tokens: &[Token], slot_id: &str, job_id: &str)
-    -> Result<(), CommandErr>
-{
+/// True if the template has any placeholder at all, as opposed to being
+/// nothing but literal characters.
+fn has_placeholder(arg_tokens: &[Token]) -> bool {
+    arg_tokens.iter().any(|x| !matches!(*x, Token::Character(_)))
+}
+
+/// Expands every token in the template against one job's input columns.
+fn render_tokens(columns: &[String], arg_tokens: &[Token], slot_id: &str, job_id: &str) -> String {
     let mut arguments = String::new();
     for arg in arg_tokens {
         match *arg {
             Token::Character(arg) => arguments.push(arg),
-            Token::Basename => arguments.push_str(input),
-            Token::BaseAndExt => arguments.push_str(basename(remove_extension(input))),
-            Token::Dirname => arguments.push_str(dirname(input)),
+            Token::Basename => arguments.push_str(basename(&whole_line(columns))),
+            Token::BaseAndExt => arguments.push_str(basename(remove_extension(&whole_line(columns)))),
+            Token::Dirname => arguments.push_str(dirname(&whole_line(columns))),
             Token::Job => arguments.push_str(job_id),
-            Token::Placeholder => arguments.push_str(input),
-            Token::RemoveExtension => arguments.push_str(remove_extension(input)),
-            Token::Slot => arguments.push_str(slot_id)
+            Token::Placeholder => arguments.push_str(&whole_line(columns)),
+            Token::RemoveExtension => arguments.push_str(remove_extension(&whole_line(columns))),
+            Token::Slot => arguments.push_str(slot_id),
+            Token::Argument(n) => arguments.push_str(column(columns, n)),
+            Token::ArgumentRemoveExtension(n) => arguments.push_str(remove_extension(column(columns, n))),
+            Token::ArgumentBasename(n) => arguments.push_str(basename(column(columns, n))),
+            Token::ArgumentDirname(n) => arguments.push_str(dirname(column(columns, n))),
+            Token::ArgumentBaseAndExt(n) => arguments.push_str(basename(remove_extension(column(columns, n))))
         }
     }
+    arguments
+}
 
-    let placeholder_exists = arg_tokens.iter().any(|ref x| {
-        x == &&Token::BaseAndExt || x == &&Token::Basename || x == &&Token::Dirname ||
-        x == &&Token::Job || x == &&Token::Placeholder || x == &&Token::RemoveExtension ||
-        x == &&Token::Slot
-    });
-
-    if !placeholder_exists {
-        arguments.push_str(input);
+/// Runs `command` with `arguments`, either as a local process, or — when
+/// `remote` is `Some((host, workdir))` — over `ssh` on that host, first
+/// `cd`-ing into `workdir` if one was given via `--workdir`.
+fn dispatch(command: &str, arguments: &[&str], remote: Option<(&str, Option<&str>)>)
+    -> io::Result<std::process::ExitStatus>
+{
+    match remote {
+        Some((host, workdir)) => {
+            let mut remote_command = String::new();
+            if let Some(dir) = workdir {
+                remote_command.push_str("cd ");
+                remote_command.push_str(dir);
+                remote_command.push_str(" && ");
+            }
+            remote_command.push_str(command);
+            for arg in arguments {
+                remote_command.push(' ');
+                remote_command.push_str(arg);
+            }
+            Command::new("ssh").arg(host).arg(remote_command).status()
+        },
+        None => Command::new(command).args(arguments).status()
     }
+}
 
-    let arguments = arguments.split_whitespace().map(|x| x.to_owned()).collect::<Vec<String>>();
-
-    if let Err(_) = Command::new(&command).args(&arguments).status() {
-        return Err(CommandErr::Failed(String::from(command), arguments));
+/// Builds the argument string for a `-N`/`-X` batch of jobs: the
+/// placeholder tokens are expanded once per job in the batch, or, if the
+/// template has no placeholder at all, every job's whole line is appended
+/// to the command instead (xargs-style).
+fn render_batch_arguments(batch: &[Vec<String>], arg_tokens: &[Token], slot_id: &str, job_id: &str) -> String {
+    let mut arguments = String::new();
+    if has_placeholder(arg_tokens) {
+        for columns in batch {
+            if !arguments.is_empty() { arguments.push(' '); }
+            arguments.push_str(&render_tokens(columns, arg_tokens, slot_id, job_id));
+        }
+    } else {
+        for arg in arg_tokens {
+            if let Token::Character(character) = *arg { arguments.push(character); }
+        }
+        for columns in batch {
+            arguments.push(' ');
+            arguments.push_str(&whole_line(columns));
+        }
     }
-    Ok(())
+    arguments
 }
 
 /// Removes the extension of a given input
@@ -264,59 +780,545 @@ fn dirname(input: &str) -> &str {
 enum ParseErr {
     JobsNaN(String),
     JobsNoValue,
-    InputVarsNotDefined,
+    BatchSizeNaN(String),
+    BatchSizeNoValue,
+    BatchSizeZero,
+    SshLoginNoValue,
+    SshLoginCapacityZero,
+    TransferFileNoValue,
+    ReturnNoValue,
+    WorkdirNoValue,
+    RetriesNaN(String),
+    RetriesNoValue,
+    RetryDelayNaN(String),
+    RetryDelayNoValue,
+    HaltOnErrorNoValue,
+    TmpdirNoValue,
+    BlockSizeNaN(String),
+    BlockSizeNoValue,
+}
+
+/// Truncates every list in a `:::`/`:::+` group down to the shortest
+/// list's length, so the group can be zipped by index. Excess arguments
+/// in a longer list are silently lost, same as a single `:::+` pairing.
+fn normalize_group(group: &mut [Vec<String>]) {
+    let shortest = group.iter().map(Vec::len).min().unwrap_or(0);
+    for list in group.iter_mut() {
+        list.truncate(shortest);
+    }
+}
+
+/// Pushes one `:::`/`:::+` input token onto `list`, expanding it first if
+/// it's a well-formed `A..B`/`A..B..S` numeric range; anything else is
+/// pushed as the literal token.
+fn push_expanded(list: &mut Vec<String>, token: &str) {
+    match expand_sequence(token) {
+        Some(values) => list.extend(values),
+        None => list.push(token.to_owned())
+    }
+}
+
+/// Expands a `A..B` or `A..B..S` token into the inclusive integer sequence
+/// from `A` to `B`, stepping by `S` (default `1`, with the sign inferred
+/// from the direction of the range so descending ranges like `10..1` work).
+/// Returns `None` for anything that isn't a well-formed numeric range, so
+/// the caller can fall back to treating the token as a literal input.
+fn expand_sequence(token: &str) -> Option<Vec<String>> {
+    let mut parts = token.split("..");
+    let start_str = parts.next()?;
+    let end_str = parts.next()?;
+    let step_str = parts.next();
+    if parts.next().is_some() { return None; }
+
+    let start: i64 = start_str.parse().ok()?;
+    let end: i64 = end_str.parse().ok()?;
+    let step: i64 = match step_str {
+        Some(s) => s.parse().ok()?,
+        None => 1
+    };
+    if step == 0 { return None; }
+    let step = if end < start { -step.abs() } else { step.abs() };
+
+    // Mirror `seq -w`: only zero-pad when both endpoints are written with
+    // the same width and at least one has a leading zero.
+    let width = if start_str.len() == end_str.len() && (has_leading_zero(start_str) || has_leading_zero(end_str)) {
+        Some(start_str.trim_start_matches('-').len())
+    } else {
+        None
+    };
+
+    let mut values = Vec::new();
+    let mut current = start;
+    loop {
+        values.push(match width {
+            Some(w) => format!("{:01$}", current, w),
+            None => current.to_string()
+        });
+        if current == end { break; }
+        current += step;
+        if (step > 0 && current > end) || (step < 0 && current < end) { break; }
+    }
+    Some(values)
+}
+
+fn has_leading_zero(token: &str) -> bool {
+    let digits = token.trim_start_matches('-');
+    digits.len() > 1 && digits.starts_with('0')
+}
+
+/// Expands `:::`/`:::+` input groups into the cartesian product of their
+/// independent lists, zipping each `:::+` list in lockstep with the rest
+/// of its group. With only one group, this is the flat list of its single
+/// list's elements, preserving the existing single-`{}` behavior.
+fn generate_jobs(groups: &[Vec<Vec<String>>]) -> Vec<Vec<String>> {
+    let mut jobs: Vec<Vec<String>> = vec![Vec::new()];
+    for group in groups {
+        let width = group.first().map(Vec::len).unwrap_or(0);
+        let mut expanded = Vec::with_capacity(jobs.len() * width);
+        for job in &jobs {
+            for index in 0..width {
+                let mut expanded_job = job.clone();
+                for list in group {
+                    expanded_job.push(list[index].clone());
+                }
+                expanded.push(expanded_job);
+            }
+        }
+        jobs = expanded;
+    }
+    jobs
 }
 
 // Parses input arguments and stores their values into their associated variabless.
-fn parse_arguments(ncores: &mut usize, command: &mut String, arg_tokens: &mut Vec<Token>,
-    input_variables: &mut Vec<String>) -> Result<(), ParseErr>
-{
-    let mut parsing_arguments = true;
-    let mut command_is_set    = false;
-    let mut raw_args = env::args().skip(1).peekable();
-    let mut comm = String::new();
-    while let Some(argument) = raw_args.next() {
-        if parsing_arguments {
-            match argument.as_str() {
-                // Defines the number of jobs to run in parallel.
-                "-j"  => {
-                    match raw_args.peek() {
-                        Some(val) => match val.parse::<usize>() {
-                            Ok(val) => *ncores = val,
-                            Err(_)  => return Err(ParseErr::JobsNaN(val.clone()))
-                        },
-                        None => return Err(ParseErr::JobsNoValue)
-                    }
-                    let _ = raw_args.next();
-                },
-                // Arguments after `:::` are input values.
-                ":::" => parsing_arguments = false,
-                _ => {
-                    if command_is_set {
-                        comm.push(' ');
-                        comm.push_str(&argument);
-                    } else {
-                        comm.push_str(&argument);
-                        command_is_set = true;
-                    }
+/// Every setting `main` needs out of the command line, gathered into one
+/// struct instead of a growing list of `&mut` out-parameters. Built with
+/// `Args::new()` and filled in by `Args::parse()`.
+struct Args {
+    ncores: usize,
+    command: String,
+    arg_tokens: Vec<Token>,
+    input_groups: Vec<Vec<Vec<String>>>,
+    act_as_jobserver: bool,
+    batch_size: usize,
+    batch_auto: bool,
+    null_terminated: bool,
+    sshlogins: Vec<sshlogin::SshLogin>,
+    transferfile: Option<String>,
+    return_file: Option<String>,
+    workdir: Option<String>,
+    retries: usize,
+    retry_delay: Duration,
+    halt_on_error: bool,
+    tmpdir: Option<PathBuf>,
+    block_size: usize,
+}
+
+impl Args {
+    fn new() -> Args {
+        Args {
+            ncores: num_cpus::get(),
+            command: String::new(),
+            arg_tokens: Vec::new(),
+            input_groups: Vec::new(),
+            act_as_jobserver: false,
+            batch_size: 1,
+            batch_auto: false,
+            null_terminated: false,
+            sshlogins: Vec::new(),
+            transferfile: None,
+            return_file: None,
+            workdir: None,
+            retries: 0,
+            retry_delay: Duration::from_millis(0),
+            halt_on_error: false,
+            tmpdir: None,
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+
+    /// Parses `env::args()` into `self`, leaving already-defaulted fields
+    /// untouched for any flag that wasn't given.
+    fn parse(&mut self) -> Result<(), ParseErr> {
+        let mut parsing_arguments = true;
+        let mut command_is_set    = false;
+        let mut raw_args = env::args().skip(1).peekable();
+        let mut comm = String::new();
+        // The group of lists currently being filled in; flushed to
+        // `input_groups` whenever a new (non-linked) `:::` list begins.
+        let mut current_group: Vec<Vec<String>> = Vec::new();
+        while let Some(argument) = raw_args.next() {
+            if parsing_arguments {
+                match argument.as_str() {
+                    // Defines the number of jobs to run in parallel.
+                    "-j"  => {
+                        match raw_args.peek() {
+                            Some(val) => match val.parse::<usize>() {
+                                Ok(val) => self.ncores = val,
+                                Err(_)  => return Err(ParseErr::JobsNaN(val.clone()))
+                            },
+                            None => return Err(ParseErr::JobsNoValue)
+                        }
+                        let _ = raw_args.next();
+                    },
+                    // Arguments after `:::` are input values, starting a new list.
+                    // `::::` is accepted as an alias for parity with GNU parallel's
+                    // own syntax; we don't distinguish inline values from a file list,
+                    // so `:::: -` reads from stdin exactly like `::: -` does.
+                    ":::" | "::::" => {
+                        parsing_arguments = false;
+                        current_group.push(Vec::new());
+                    },
+                    // Act as the jobserver for nested `parallel`/`make` invocations.
+                    "--jobserver" => self.act_as_jobserver = true,
+                    // Groups a fixed number of inputs into each invocation.
+                    "-N" => {
+                        match raw_args.peek() {
+                            Some(val) => match val.parse::<usize>() {
+                                Ok(0)   => return Err(ParseErr::BatchSizeZero),
+                                Ok(val) => self.batch_size = val,
+                                Err(_)  => return Err(ParseErr::BatchSizeNaN(val.clone()))
+                            },
+                            None => return Err(ParseErr::BatchSizeNoValue)
+                        }
+                        let _ = raw_args.next();
+                    },
+                    // Groups as many inputs as will fit under the command line's
+                    // `ARG_MAX` into each invocation, like xargs' `-x`.
+                    "-X" => self.batch_auto = true,
+                    // Splits stdin records on NUL instead of newline, for
+                    // filenames that may contain whitespace.
+                    "-0" | "--null" => self.null_terminated = true,
+                    // Registers a remote host (`user@host`, or `N/user@host`
+                    // to override its capacity) to dispatch jobs to over ssh.
+                    // Once any host is registered, every job runs remotely;
+                    // none runs on the local cores.
+                    "--sshlogin" => {
+                        let val = raw_args.peek().ok_or(ParseErr::SshLoginNoValue)?;
+                        self.sshlogins.push(sshlogin::SshLogin::parse(val).ok_or(ParseErr::SshLoginCapacityZero)?);
+                        let _ = raw_args.next();
+                    },
+                    // Copies a file to each remote host before running the job there.
+                    "--transferfile" => {
+                        let val = raw_args.peek().ok_or(ParseErr::TransferFileNoValue)?;
+                        self.transferfile = Some(val.clone());
+                        let _ = raw_args.next();
+                    },
+                    // Copies a file back from each remote host after the job finishes.
+                    "--return" => {
+                        let val = raw_args.peek().ok_or(ParseErr::ReturnNoValue)?;
+                        self.return_file = Some(val.clone());
+                        let _ = raw_args.next();
+                    },
+                    // Changes into this directory on the remote host before running the job.
+                    "--workdir" => {
+                        let val = raw_args.peek().ok_or(ParseErr::WorkdirNoValue)?;
+                        self.workdir = Some(val.clone());
+                        let _ = raw_args.next();
+                    },
+                    // Re-runs a job that exits non-zero up to this many times
+                    // before reporting it as failed.
+                    "--retries" => {
+                        match raw_args.peek() {
+                            Some(val) => match val.parse::<usize>() {
+                                Ok(val) => self.retries = val,
+                                Err(_)  => return Err(ParseErr::RetriesNaN(val.clone()))
+                            },
+                            None => return Err(ParseErr::RetriesNoValue)
+                        }
+                        let _ = raw_args.next();
+                    },
+                    // How long to wait, in seconds, before re-running a retried job.
+                    "--retry-delay" => {
+                        match raw_args.peek() {
+                            Some(val) => match val.parse::<f64>() {
+                                Ok(seconds) => self.retry_delay = Duration::from_millis((seconds * 1000f64) as u64),
+                                Err(_)      => return Err(ParseErr::RetryDelayNaN(val.clone()))
+                            },
+                            None => return Err(ParseErr::RetryDelayNoValue)
+                        }
+                        let _ = raw_args.next();
+                    },
+                    // Stops launching new jobs as soon as one fails after
+                    // exhausting its retries, and propagates its exit status as
+                    // our own. GNU parallel's percentage-based halt modes are
+                    // accepted but not distinguished; any job failure halts.
+                    "--halt-on-error" => {
+                        let _mode = raw_args.peek().ok_or(ParseErr::HaltOnErrorNoValue)?;
+                        self.halt_on_error = true;
+                        let _ = raw_args.next();
+                    },
+                    // Where a `:::` input list large enough to cross
+                    // `--block-size` spills to disk instead of staying
+                    // entirely in memory. Defaults to the system temp directory.
+                    "--tmpdir" => {
+                        let val = raw_args.peek().ok_or(ParseErr::TmpdirNoValue)?;
+                        self.tmpdir = Some(PathBuf::from(val));
+                        let _ = raw_args.next();
+                    },
+                    // How many bytes of a `:::` input list may accumulate in
+                    // memory before it spills to `--tmpdir`.
+                    "--block-size" => {
+                        match raw_args.peek() {
+                            Some(val) => match val.parse::<usize>() {
+                                Ok(val) => self.block_size = val,
+                                Err(_)  => return Err(ParseErr::BlockSizeNaN(val.clone()))
+                            },
+                            None => return Err(ParseErr::BlockSizeNoValue)
+                        }
+                        let _ = raw_args.next();
+                    },
+                    _ => {
+                        if command_is_set {
+                            comm.push(' ');
+                            comm.push_str(&argument);
+                        } else {
+                            comm.push_str(&argument);
+                            command_is_set = true;
+                        }
 
+                    }
+                }
+            } else {
+                match argument.as_str() {
+                    // `:::` starts a new list, multiplying the cartesian product.
+                    // `::::` is accepted as the same alias as above.
+                    ":::" | "::::" => {
+                        if !current_group.is_empty() {
+                            normalize_group(&mut current_group);
+                            self.input_groups.push(current_group);
+                            current_group = Vec::new();
+                        }
+                        current_group.push(Vec::new());
+                    },
+                    // `:::+` links the next list to the current group instead,
+                    // zipping it pairwise rather than multiplying. `::::+` is the
+                    // same alias parity as `::::`/`:::`.
+                    ":::+" | "::::+" => current_group.push(Vec::new()),
+                    _ => push_expanded(current_group.last_mut().unwrap(), &argument)
                 }
             }
-        } else {
-            input_variables.push(argument);
         }
+
+        if !current_group.is_empty() {
+            normalize_group(&mut current_group);
+            self.input_groups.push(current_group);
+        }
+
+        // This will fill in command and argument information needed by the threads.
+        // If there is a space in the argument, then the command has arguments
+        match comm.chars().position(|x| x == ' ') {
+            Some(pos) => {
+                self.command    = String::from(&comm[0..pos]);
+                self.arg_tokens = tokenize(&comm[pos+1..]);
+            },
+            None => self.command = comm
+        }
+
+        // An empty `input_groups` is not an error: it means no `:::` list was
+        // given, so `main` falls back to streaming records from stdin instead.
+        Ok(())
     }
+}
 
-    // This will fill in command and argument information needed by the threads.
-    // If there is a space in the argument, then the command has arguments
-    match comm.chars().position(|x| x == ' ') {
-        Some(pos) => {
-            *command    = String::from(&comm[0..pos]);
-            *arg_tokens = tokenize(&comm[pos+1..]);
-        },
-        None => *command = comm
+#[cfg(test)]
+mod expand_sequence_tests {
+    use super::*;
+
+    #[test]
+    fn expands_an_ascending_range_with_the_default_step() {
+        assert_eq!(expand_sequence("1..4"), Some(["1", "2", "3", "4"].iter().map(|s| s.to_string()).collect()));
+    }
+
+    #[test]
+    fn expands_a_descending_range_by_inferring_a_negative_step() {
+        assert_eq!(expand_sequence("4..1"), Some(["4", "3", "2", "1"].iter().map(|s| s.to_string()).collect()));
+    }
+
+    #[test]
+    fn expands_with_an_explicit_step() {
+        assert_eq!(expand_sequence("1..10..3"), Some(["1", "4", "7", "10"].iter().map(|s| s.to_string()).collect()));
+    }
+
+    #[test]
+    fn zero_pads_when_both_endpoints_share_a_leading_zero_width() {
+        assert_eq!(expand_sequence("01..03"), Some(["01", "02", "03"].iter().map(|s| s.to_string()).collect()));
+    }
+
+    #[test]
+    fn does_not_pad_when_widths_differ_or_neither_has_a_leading_zero() {
+        assert_eq!(expand_sequence("1..10"), Some(["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"]
+            .iter().map(|s| s.to_string()).collect()));
+    }
+
+    #[test]
+    fn rejects_a_zero_step() {
+        assert_eq!(expand_sequence("1..5..0"), None);
+    }
+
+    #[test]
+    fn rejects_non_numeric_tokens_as_a_literal() {
+        assert_eq!(expand_sequence("hello"), None);
+        assert_eq!(expand_sequence("a..b"), None);
+    }
+
+    #[test]
+    fn push_expanded_falls_back_to_the_literal_token_when_not_a_range() {
+        let mut list = Vec::new();
+        push_expanded(&mut list, "file.txt");
+        assert_eq!(list, vec!["file.txt".to_string()]);
+    }
+
+    #[test]
+    fn push_expanded_extends_with_a_range() {
+        let mut list = Vec::new();
+        push_expanded(&mut list, "1..3");
+        assert_eq!(list, vec!["1".to_string(), "2".to_string(), "3".to_string()]);
     }
+}
+
+#[cfg(test)]
+mod input_group_tests {
+    use super::*;
+
+    fn list(values: &[&str]) -> Vec<String> { values.iter().map(|v| v.to_string()).collect() }
 
-    if input_variables.is_empty() { return Err(ParseErr::InputVarsNotDefined) }
-    Ok(())
+    #[test]
+    fn generate_jobs_takes_the_cartesian_product_of_unlinked_lists() {
+        let groups = vec![
+            vec![list(&["a", "b"])],
+            vec![list(&["1", "2"])],
+        ];
+        assert_eq!(generate_jobs(&groups), vec![
+            list(&["a", "1"]), list(&["a", "2"]),
+            list(&["b", "1"]), list(&["b", "2"]),
+        ]);
+    }
+
+    #[test]
+    fn generate_jobs_zips_a_linked_list_instead_of_multiplying() {
+        // A `:::+`-linked group holds two lists that must stay in
+        // lockstep, rather than being multiplied against each other.
+        let groups = vec![vec![list(&["a", "b"]), list(&["1", "2"])]];
+        assert_eq!(generate_jobs(&groups), vec![list(&["a", "1"]), list(&["b", "2"])]);
+    }
+
+    #[test]
+    fn generate_jobs_with_a_single_list_is_just_that_list() {
+        let groups = vec![vec![list(&["a", "b", "c"])]];
+        assert_eq!(generate_jobs(&groups), vec![list(&["a"]), list(&["b"]), list(&["c"])]);
+    }
+
+    #[test]
+    fn normalize_group_truncates_to_the_shortest_list() {
+        let mut group = vec![list(&["a", "b", "c"]), list(&["1", "2"])];
+        normalize_group(&mut group);
+        assert_eq!(group, vec![list(&["a", "b"]), list(&["1", "2"])]);
+    }
+}
+
+#[cfg(test)]
+mod disk_spill_tests {
+    use super::*;
+
+    #[test]
+    fn spill_to_disk_round_trips_every_row_in_order() {
+        let data = vec![list(&["a", "b"]), list(&["c", "d"]), list(&["e", "f"])];
+        let (mut file, offsets) = spill_to_disk(&data, None).unwrap();
+        assert_eq!(read_rows(&mut file, &offsets, 0, data.len()), data);
+    }
+
+    #[test]
+    fn read_rows_preserves_columns_that_contain_spaces() {
+        let data = vec![list(&["has space", "plain"])];
+        let (mut file, offsets) = spill_to_disk(&data, None).unwrap();
+        assert_eq!(read_rows(&mut file, &offsets, 0, 1), data);
+    }
+
+    #[test]
+    fn read_rows_fetches_an_arbitrary_contiguous_slice() {
+        let data = vec![list(&["1"]), list(&["2"]), list(&["3"]), list(&["4"])];
+        let (mut file, offsets) = spill_to_disk(&data, None).unwrap();
+        assert_eq!(read_rows(&mut file, &offsets, 1, 3), vec![list(&["2"]), list(&["3"])]);
+    }
+
+    fn list(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod batch_tests {
+    use super::*;
+
+    #[test]
+    fn claim_batch_under_fixed_n_splits_into_even_groups_with_a_short_last_one() {
+        let counter = AtomicUsize::new(0);
+        let row_len = |_: usize| 1;
+        assert_eq!(claim_batch(&counter, 5, 2, None, row_len), Some((0, 2)));
+        assert_eq!(claim_batch(&counter, 5, 2, None, row_len), Some((2, 4)));
+        assert_eq!(claim_batch(&counter, 5, 2, None, row_len), Some((4, 5)));
+        assert_eq!(claim_batch(&counter, 5, 2, None, row_len), None);
+    }
+
+    #[test]
+    fn claim_batch_under_x_grows_until_the_byte_budget_would_be_crossed() {
+        let counter = AtomicUsize::new(0);
+        // Rows of length 3 (plus a joining byte each): a budget of 7
+        // fits two rows (3 + 1 + 3 = 7) but not a third.
+        let row_len = |_: usize| 3;
+        assert_eq!(claim_batch(&counter, 10, 1, Some(7), row_len), Some((0, 2)));
+    }
+
+    #[test]
+    fn claim_batch_always_takes_at_least_one_row_even_over_budget() {
+        let counter = AtomicUsize::new(0);
+        let row_len = |_: usize| 100;
+        assert_eq!(claim_batch(&counter, 3, 1, Some(1), row_len), Some((0, 1)));
+    }
+
+    #[test]
+    fn has_placeholder_is_false_for_literal_only_templates() {
+        assert!(!has_placeholder(&tokenize("echo hello")));
+        assert!(has_placeholder(&tokenize("echo {}")));
+    }
+}
+
+#[cfg(test)]
+mod retry_halt_tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    fn exit_status(code: i32) -> ExitStatus { ExitStatus::from_raw(code << 8) }
+
+    #[test]
+    fn run_with_retries_gives_up_after_the_last_attempt() {
+        let mut attempts = 0;
+        let code = run_with_retries(|| {
+            attempts += 1;
+            Ok(exit_status(1))
+        }, 2, Duration::from_millis(0));
+        assert_eq!(code, 1);
+        assert_eq!(attempts, 3); // the initial try plus 2 retries
+    }
+
+    #[test]
+    fn run_with_retries_stops_as_soon_as_one_succeeds() {
+        let mut attempts = 0;
+        let code = run_with_retries(|| {
+            attempts += 1;
+            Ok(exit_status(if attempts == 2 { 0 } else { 1 }))
+        }, 5, Duration::from_millis(0));
+        assert_eq!(code, 0);
+        assert_eq!(attempts, 2);
+    }
+
+    #[test]
+    fn register_halt_keeps_the_first_exit_code() {
+        let halt = Arc::new(HaltState::new());
+        register_halt(&Some(halt.clone()), 3);
+        register_halt(&Some(halt.clone()), 7);
+        assert!(halt.triggered.load(Ordering::SeqCst));
+        assert_eq!(halt.exit_code.load(Ordering::SeqCst), 3);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,77 @@
+//! Raises the process's soft `RLIMIT_NOFILE` toward the hard limit before
+//! jobs start spawning, so that large `-j`/`--jobs` values (each holding
+//! stdin/stdout/stderr plus the unprocessed disk-buffer file) don't run
+//! into "too many open files" under a restrictive default soft limit.
+//!
+//! Every syscall here is treated as best-effort: any failure leaves the
+//! limit untouched and the program keeps running under whatever cap the
+//! environment already imposed.
+
+use std::mem;
+
+/// Raises the soft `RLIMIT_NOFILE` to the hard limit, clamped to
+/// `kern.maxfilesperproc` on macOS (where the reported hard limit is
+/// effectively unbounded). Never lowers an already-higher limit. Returns
+/// the soft limit in effect afterward, or `None` if it could not be read.
+pub fn raise_fd_limit() -> Option<u64> {
+    unsafe {
+        let mut rlim: libc::rlimit = mem::zeroed();
+        if libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlim) != 0 {
+            return None;
+        }
+
+        let mut target = rlim.rlim_max;
+
+        if cfg!(target_os = "macos") {
+            if let Some(maxfilesperproc) = macos_maxfilesperproc() {
+                target = target.min(maxfilesperproc);
+            }
+        }
+
+        if target <= rlim.rlim_cur {
+            return Some(rlim.rlim_cur as u64);
+        }
+
+        let current = rlim.rlim_cur;
+        rlim.rlim_cur = target;
+        if libc::setrlimit(libc::RLIMIT_NOFILE, &rlim) != 0 {
+            return Some(current as u64);
+        }
+
+        Some(rlim.rlim_cur as u64)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn macos_maxfilesperproc() -> Option<libc::rlim_t> {
+    use std::ffi::CString;
+    use std::ptr;
+
+    unsafe {
+        let name = CString::new("kern.maxfilesperproc").ok()?;
+        let mut value: libc::c_int = 0;
+        let mut size = mem::size_of::<libc::c_int>();
+        let result = libc::sysctlbyname(name.as_ptr(), &mut value as *mut _ as *mut libc::c_void,
+            &mut size, ptr::null_mut(), 0);
+        if result == 0 { Some(value as libc::rlim_t) } else { None }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn macos_maxfilesperproc() -> Option<libc::rlim_t> { None }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raise_fd_limit_never_lowers_the_current_soft_limit() {
+        let before = unsafe {
+            let mut rlim: libc::rlimit = mem::zeroed();
+            assert_eq!(libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlim), 0);
+            rlim.rlim_cur as u64
+        };
+        let after = raise_fd_limit().expect("getrlimit should succeed in a test process");
+        assert!(after >= before);
+    }
+}
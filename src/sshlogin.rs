@@ -0,0 +1,103 @@
+//! Parses `--sshlogin` targets and schedules jobs across them, so that
+//! `parallel` can dispatch work to remote hosts. Once any host is
+//! registered, every job is dispatched to a remote host; none runs on the
+//! local cores.
+
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// One `--sshlogin [N/]user@host` target and the number of concurrent
+/// jobs it is allowed to run at once.
+pub struct SshLogin {
+    pub host: String,
+    pub capacity: usize,
+    in_use: AtomicUsize,
+}
+
+impl SshLogin {
+    /// Parses `user@host`, or `N/user@host` where `N` overrides the
+    /// default capacity of one concurrent job for that host. Returns
+    /// `None` for a `0/user@host` capacity, since such a host could never
+    /// be acquired (and `Scheduler::acquire` would spin forever if every
+    /// registered host had zero capacity).
+    pub fn parse(argument: &str) -> Option<SshLogin> {
+        match argument.find('/') {
+            Some(pos) if argument[..pos].parse::<usize>().is_ok() => {
+                let capacity = argument[..pos].parse().unwrap();
+                if capacity == 0 { return None; }
+                Some(SshLogin { host: argument[pos+1..].to_owned(), capacity, in_use: AtomicUsize::new(0) })
+            },
+            _ => Some(SshLogin { host: argument.to_owned(), capacity: 1, in_use: AtomicUsize::new(0) })
+        }
+    }
+
+    fn try_acquire<'a>(&self, scheduler: &'a Scheduler, index: usize) -> Option<SshSlot<'a>> {
+        loop {
+            let current = self.in_use.load(Ordering::SeqCst);
+            if current >= self.capacity { return None; }
+            if self.in_use.compare_exchange(current, current + 1, Ordering::SeqCst, Ordering::SeqCst) == Ok(current) {
+                return Some(SshSlot { scheduler, index });
+            }
+        }
+    }
+}
+
+/// Tracks per-host capacity, the same way the local worker pool tracks
+/// its core count, and round-robins jobs across whichever host has a
+/// free slot.
+pub struct Scheduler {
+    logins: Vec<SshLogin>,
+    next:   AtomicUsize,
+}
+
+impl Scheduler {
+    pub fn new(logins: Vec<SshLogin>) -> Scheduler {
+        Scheduler { logins, next: AtomicUsize::new(0) }
+    }
+
+    /// Round-robins across the configured hosts, blocking until one with
+    /// spare capacity is found.
+    pub fn acquire(&self) -> SshSlot<'_> {
+        loop {
+            let start = self.next.fetch_add(1, Ordering::SeqCst) % self.logins.len();
+            if let Some(slot) = self.logins[start].try_acquire(self, start) {
+                return slot;
+            }
+            thread::yield_now();
+        }
+    }
+}
+
+/// A claimed slot on one remote host. Releases its capacity back to the
+/// scheduler when dropped.
+pub struct SshSlot<'a> {
+    scheduler: &'a Scheduler,
+    index:     usize,
+}
+
+impl<'a> SshSlot<'a> {
+    pub fn host(&self) -> &str { &self.scheduler.logins[self.index].host }
+}
+
+impl<'a> Drop for SshSlot<'a> {
+    fn drop(&mut self) {
+        self.scheduler.logins[self.index].in_use.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Copies `path` to the same path on `host` via `rsync`, for `--transferfile`.
+pub fn transfer_to(host: &str, path: &str) -> Result<(), String> {
+    rsync(path, &format!("{}:{}", host, path))
+}
+
+/// Copies `path` back from `host` via `rsync`, for `--return`.
+pub fn transfer_from(host: &str, path: &str) -> Result<(), String> {
+    rsync(&format!("{}:{}", host, path), path)
+}
+
+fn rsync(from: &str, to: &str) -> Result<(), String> {
+    let status = Command::new("rsync").arg("-az").arg(from).arg(to).status()
+        .map_err(|why| why.to_string())?;
+    if status.success() { Ok(()) } else { Err(format!("rsync exited with {}", status)) }
+}